@@ -0,0 +1,158 @@
+//! Derive macro for strongly-typed posthog-rs events.
+//!
+//! `#[derive(PostHogEvent)]` turns a struct into something that converts into
+//! the dynamic `posthog::Event`: each field becomes a property (serialized
+//! through the same `serde_json` path `insert_prop` uses) and a container
+//! attribute sets the event name.
+//!
+//! ```ignore
+//! #[derive(PostHogEvent)]
+//! #[posthog(event = "user signed up")]
+//! struct SignedUp {
+//!     #[posthog(distinct_id)]
+//!     user_id: String,
+//!     #[posthog(rename = "plan")]
+//!     plan_name: String,
+//!     #[posthog(skip)]
+//!     internal: bool,
+//! }
+//! ```
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitStr};
+
+#[proc_macro_derive(PostHogEvent, attributes(posthog))]
+pub fn derive_posthog_event(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let ident = &input.ident;
+
+    let event_name = container_event_name(&input)?.unwrap_or_else(|| ident.to_string());
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(named) => &named.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "PostHogEvent can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "PostHogEvent can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut distinct_id_field = None;
+    let mut inserts = Vec::new();
+    for field in fields {
+        let field_ident = field.ident.as_ref().expect("named field");
+        let attrs = FieldAttrs::parse(field)?;
+
+        if attrs.distinct_id {
+            if distinct_id_field.is_some() {
+                return Err(syn::Error::new_spanned(
+                    field_ident,
+                    "only one field may be marked #[posthog(distinct_id)]",
+                ));
+            }
+            distinct_id_field = Some(field_ident.clone());
+            // The distinct id is not also emitted as a property.
+            continue;
+        }
+
+        if attrs.skip {
+            continue;
+        }
+
+        let prop_name = attrs.rename.unwrap_or_else(|| field_ident.to_string());
+        inserts.push(quote! {
+            ::posthog_rs::EventBase::insert_prop(&mut event, #prop_name, self.#field_ident)?;
+        });
+    }
+
+    let distinct_id = distinct_id_field.ok_or_else(|| {
+        syn::Error::new_spanned(
+            ident,
+            "PostHogEvent requires exactly one field marked #[posthog(distinct_id)]",
+        )
+    })?;
+
+    Ok(quote! {
+        impl ::posthog_rs::PostHogEvent for #ident {
+            fn into_event(self) -> ::core::result::Result<::posthog_rs::Event, ::posthog_rs::Error> {
+                let mut event = ::posthog_rs::Event::new(
+                    #event_name.to_string(),
+                    ::std::string::ToString::to_string(&self.#distinct_id),
+                );
+                #(#inserts)*
+                ::core::result::Result::Ok(event)
+            }
+        }
+    })
+}
+
+/// Reads the `#[posthog(event = "...")]` container attribute, if present.
+fn container_event_name(input: &DeriveInput) -> syn::Result<Option<String>> {
+    let mut name = None;
+    for attr in &input.attrs {
+        if !attr.path().is_ident("posthog") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("event") {
+                let value: LitStr = meta.value()?.parse()?;
+                name = Some(value.value());
+                Ok(())
+            } else {
+                Err(meta.error("unsupported posthog container attribute"))
+            }
+        })?;
+    }
+    Ok(name)
+}
+
+#[derive(Default)]
+struct FieldAttrs {
+    skip: bool,
+    distinct_id: bool,
+    rename: Option<String>,
+}
+
+impl FieldAttrs {
+    fn parse(field: &syn::Field) -> syn::Result<Self> {
+        let mut attrs = FieldAttrs::default();
+        for attr in &field.attrs {
+            if !attr.path().is_ident("posthog") {
+                continue;
+            }
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    attrs.skip = true;
+                    Ok(())
+                } else if meta.path.is_ident("distinct_id") {
+                    attrs.distinct_id = true;
+                    Ok(())
+                } else if meta.path.is_ident("rename") {
+                    let value: LitStr = meta.value()?.parse()?;
+                    attrs.rename = Some(value.value());
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported posthog field attribute"))
+                }
+            })?;
+        }
+        Ok(attrs)
+    }
+}
@@ -1,11 +1,18 @@
+use std::thread;
 use std::time::Duration;
 use std::panic;
 
-use reqwest::{blocking::Client as HttpClient, header::CONTENT_TYPE};
+use reqwest::{
+    blocking::Client as HttpClient, header::AUTHORIZATION, header::CONTENT_TYPE, header::RETRY_AFTER,
+};
 
-use crate::{event::InnerEvent, Error, Event, Exception};
+use crate::event::{InnerEvent, PostHogEvent};
+use crate::feature_flags::{DecideResponse, FlagDefinition, FlagValue};
+use crate::{Error, Event, Exception};
 
-use super::{ClientOptions, exception_from_panic_info};
+use super::{
+    backoff_delay, exception_from_panic_info, is_retryable_status, parse_retry_after, ClientOptions,
+};
 
 #[derive(Clone)]
 pub struct Client {
@@ -14,42 +21,194 @@ pub struct Client {
 }
 
 impl Client {
-    pub fn capture(&self, event: Event) -> Result<(), Error> {
+    /// The options this client was built with, used by the queueing layer.
+    pub(crate) fn options(&self) -> &ClientOptions {
+        &self.options
+    }
+
+    pub fn capture<E: PostHogEvent>(&self, event: E) -> Result<(), Error> {
+        let mut event = event.into_event()?;
+        event.validate(
+            &self.options.api_key,
+            self.options.max_event_size,
+            self.options.max_event_properties,
+            self.options.drop_invalid_properties,
+        )?;
         let inner_event = InnerEvent::new(event, self.options.api_key.clone());
 
         let payload =
             serde_json::to_string(&inner_event).map_err(|e| Error::Serialization(e.to_string()))?;
 
-        self.client
-            .post(&self.options.api_endpoint)
-            .header(CONTENT_TYPE, "application/json")
-            .body(payload)
-            .send()
-            .map_err(|e| Error::Connection(e.to_string()))?;
-
-        Ok(())
+        self.send_with_retry(payload)
     }
 
     pub fn capture_batch(&self, events: Vec<Event>) -> Result<(), Error> {
         let events: Vec<_> = events
             .into_iter()
-            .map(|event| InnerEvent::new(event, self.options.api_key.clone()))
-            .collect();
+            .map(|mut event| {
+                event.validate(
+                    &self.options.api_key,
+                    self.options.max_event_size,
+                    self.options.max_event_properties,
+                    self.options.drop_invalid_properties,
+                )?;
+                Ok(InnerEvent::new(event, self.options.api_key.clone()))
+            })
+            .collect::<Result<_, Error>>()?;
 
         let payload =
             serde_json::to_string(&events).map_err(|e| Error::Serialization(e.to_string()))?;
 
-        self.client
-            .post(&self.options.api_endpoint)
+        self.send_with_retry(payload)
+    }
+
+    /// POSTs `payload` to the capture endpoint, retrying connection failures,
+    /// 5xx and 429 responses with exponential backoff (up to
+    /// [`ClientOptions::max_retries`]). Non-retryable 4xx responses fail
+    /// immediately with [`Error::Api`].
+    fn send_with_retry(&self, payload: String) -> Result<(), Error> {
+        let mut attempt = 0;
+        loop {
+            let response = self
+                .client
+                .post(&self.options.api_endpoint)
+                .header(CONTENT_TYPE, "application/json")
+                .body(payload.clone())
+                .send();
+
+            let (retryable, error, retry_after) = match response {
+                Ok(response) => {
+                    let status = response.status();
+                    if status.is_success() {
+                        return Ok(());
+                    }
+                    let retry_after = parse_retry_after(
+                        response
+                            .headers()
+                            .get(RETRY_AFTER)
+                            .and_then(|v| v.to_str().ok()),
+                    );
+                    let status = status.as_u16();
+                    let body = response.text().unwrap_or_default();
+                    (
+                        is_retryable_status(status),
+                        Error::Api { status, body },
+                        retry_after,
+                    )
+                }
+                Err(e) => (true, Error::Connection(e.to_string()), None),
+            };
+
+            if !retryable || attempt >= self.options.max_retries {
+                return Err(error);
+            }
+
+            thread::sleep(backoff_delay(&self.options, attempt, retry_after));
+            attempt += 1;
+        }
+    }
+
+    /// Resolves `key` for `distinct_id` against the remote `/flags` endpoint.
+    pub fn is_feature_enabled(&self, key: &str, distinct_id: &str) -> Result<bool, Error> {
+        Ok(self
+            .get_feature_flag(key, distinct_id)?
+            .map(|value| value.is_enabled())
+            .unwrap_or(false))
+    }
+
+    /// Returns the resolved value of `key` for `distinct_id`, or `None` when the
+    /// flag is unknown.
+    pub fn get_feature_flag(
+        &self,
+        key: &str,
+        distinct_id: &str,
+    ) -> Result<Option<FlagValue>, Error> {
+        Ok(self.decide(distinct_id)?.flag(key))
+    }
+
+    /// Returns the payload configured for `key` as resolved for `distinct_id`.
+    pub fn get_feature_flag_payload(
+        &self,
+        key: &str,
+        distinct_id: &str,
+    ) -> Result<Option<serde_json::Value>, Error> {
+        Ok(self.decide(distinct_id)?.feature_flag_payloads.get(key).cloned())
+    }
+
+    fn decide(&self, distinct_id: &str) -> Result<DecideResponse, Error> {
+        let payload = serde_json::to_string(&serde_json::json!({
+            "api_key": self.options.api_key,
+            "distinct_id": distinct_id,
+            "groups": serde_json::Map::new(),
+            "person_properties": serde_json::Map::new(),
+        }))
+        .map_err(|e| Error::Serialization(e.to_string()))?;
+
+        let response = self
+            .client
+            .post(&self.options.flags_endpoint)
             .header(CONTENT_TYPE, "application/json")
             .body(payload)
             .send()
             .map_err(|e| Error::Connection(e.to_string()))?;
 
-        Ok(())
+        let status = response.status();
+        let body = response.text().map_err(|e| Error::Connection(e.to_string()))?;
+        if !status.is_success() {
+            return Err(Error::Api {
+                status: status.as_u16(),
+                body,
+            });
+        }
+
+        serde_json::from_str(&body).map_err(|e| Error::Serialization(e.to_string()))
+    }
+
+    pub(crate) fn poll_interval(&self) -> Duration {
+        self.options.flag_poll_interval
+    }
+
+    /// Pulls the full set of flag definitions used for local evaluation. Needs
+    /// [`ClientOptions::personal_api_key`] to be set.
+    pub(crate) fn fetch_flag_definitions(&self) -> Result<Vec<FlagDefinition>, Error> {
+        let personal_api_key = self
+            .options
+            .personal_api_key
+            .as_ref()
+            .ok_or_else(|| {
+                Error::Connection("personal_api_key is required for local evaluation".into())
+            })?;
+
+        let response = self
+            .client
+            .get(&self.options.flag_definitions_endpoint)
+            .header(AUTHORIZATION, format!("Bearer {personal_api_key}"))
+            .send()
+            .map_err(|e| Error::Connection(e.to_string()))?;
+
+        let status = response.status();
+        let body = response.text().map_err(|e| Error::Connection(e.to_string()))?;
+        if !status.is_success() {
+            return Err(Error::Api {
+                status: status.as_u16(),
+                body,
+            });
+        }
+
+        #[derive(serde::Deserialize)]
+        struct Definitions {
+            #[serde(default)]
+            flags: Vec<FlagDefinition>,
+        }
+        let definitions: Definitions =
+            serde_json::from_str(&body).map_err(|e| Error::Serialization(e.to_string()))?;
+        Ok(definitions.flags)
     }
 
-    pub fn capture_exception(&self, exception: Exception) -> Result<(), Error> {
+    pub fn capture_exception(&self, mut exception: Exception) -> Result<(), Error> {
+        if self.options.capture_stack_trace {
+            exception.resolve_stack_trace();
+        }
         let event = exception.to_event();
         self.capture(event)
     }
@@ -57,7 +216,12 @@ impl Client {
     pub fn capture_exception_batch(&self, exceptions: Vec<Exception>) -> Result<(), Error> {
         let events: Vec<_> = exceptions
             .into_iter()
-            .map(|exception| exception.to_event())
+            .map(|mut exception| {
+                if self.options.capture_stack_trace {
+                    exception.resolve_stack_trace();
+                }
+                exception.to_event()
+            })
             .collect();
         self.capture_batch(events)
     }
@@ -78,7 +242,11 @@ pub fn client<C: Into<ClientOptions>>(options: C) -> Client {
         let panic_reporter_client = client.clone();
         let next = panic::take_hook();
         panic::set_hook(Box::new(move |info| {
-            let mut exception = exception_from_panic_info(info, &panic_reporter_client.options.default_distinct_id);
+            let mut exception = exception_from_panic_info(
+                info,
+                &panic_reporter_client.options.default_distinct_id,
+                panic_reporter_client.options.capture_stack_trace,
+            );
             if panic_reporter_client.options.on_panic_exception.is_some() {
                 panic_reporter_client.options.on_panic_exception.as_ref().unwrap()(&mut exception)
             }
@@ -4,6 +4,7 @@ use derive_builder::Builder;
 use std::sync::Arc;
 use std::panic::PanicHookInfo;
 use std::fmt::{Display, Formatter};
+use std::time::Duration;
 
 #[cfg(not(feature = "async-client"))]
 mod blocking;
@@ -19,6 +20,9 @@ pub use async_client::client;
 #[cfg(feature = "async-client")]
 pub use async_client::Client;
 
+mod queue;
+pub use queue::QueueingClient;
+
 #[derive(Builder, Clone)]
 pub struct ClientOptions {
     #[builder(default = "API_ENDPOINT.to_string()")]
@@ -32,6 +36,68 @@ pub struct ClientOptions {
     default_distinct_id: String,
     #[builder(default = "true")]
     enable_panic_capturing: bool,
+    /// Capture and symbolicate a stack trace for each [`Exception`]. Symbol
+    /// resolution is slow, so this can be turned off on hot paths.
+    #[builder(default = "true")]
+    capture_stack_trace: bool,
+
+    /// Maximum number of times a request is retried after a retryable failure
+    /// (connection error, 5xx, or 429). `0` disables retrying.
+    #[builder(default = "3")]
+    max_retries: u32,
+    /// Delay before the first retry; multiplied by [`backoff_multiplier`] on
+    /// each subsequent attempt.
+    ///
+    /// [`backoff_multiplier`]: ClientOptions::backoff_multiplier
+    #[builder(default = "Duration::from_millis(500)")]
+    initial_backoff: Duration,
+    /// Factor the backoff is multiplied by after each retry.
+    #[builder(default = "2.0")]
+    backoff_multiplier: f64,
+
+    /// Number of buffered events that triggers an immediate flush in
+    /// [`QueueingClient`](crate::QueueingClient).
+    #[builder(default = "1000")]
+    max_queue_size: usize,
+    /// Longest a buffered event waits before the background flush fires.
+    #[builder(default = "Duration::from_secs(10)")]
+    flush_interval: Duration,
+    /// Largest number of events sent in a single `capture_batch` request.
+    #[builder(default = "100")]
+    max_batch_size: usize,
+
+    /// Endpoint queried for flag decisions in remote mode.
+    #[builder(default = "\"https://us.i.posthog.com/flags/\".to_string()")]
+    flags_endpoint: String,
+    /// Endpoint the local-evaluation poller pulls flag definitions from.
+    #[builder(default = "\"https://us.i.posthog.com/api/feature_flag/local_evaluation/\".to_string()")]
+    flag_definitions_endpoint: String,
+    /// Personal API key used to authenticate local-evaluation requests. Local
+    /// evaluation is unavailable without it.
+    #[builder(default)]
+    personal_api_key: Option<String>,
+    /// How often the local-evaluation poller refreshes flag definitions.
+    #[builder(default = "Duration::from_secs(30)")]
+    flag_poll_interval: Duration,
+
+    /// Largest serialized size, in bytes, a single event may reach before
+    /// [`capture`](Client::capture) rejects it. PostHog's ingestion endpoint
+    /// drops events over roughly 1MB with an opaque 400, so the default mirrors
+    /// that limit and surfaces the failure locally as
+    /// [`Error::Validation`](crate::Error::Validation).
+    #[builder(default = "1_000_000")]
+    max_event_size: usize,
+    /// Largest number of user-supplied properties a single event may carry.
+    #[builder(default = "1024")]
+    max_event_properties: usize,
+    /// How collisions with the reserved `$`-prefixed property keys the crate
+    /// sets itself (`$lib`, `$lib_version`, `$exception_list`, ...) are handled:
+    /// when `true` the offending properties are dropped and the event is still
+    /// sent, when `false` the event is rejected with
+    /// [`Error::Validation`](crate::Error::Validation).
+    #[builder(default = "false")]
+    drop_invalid_properties: bool,
+
     on_panic_exception: Option<Arc<dyn Fn(&mut Exception) + Send + Sync>>,
 }
 
@@ -44,10 +110,39 @@ impl From<&str> for ClientOptions {
     }
 }
 
-fn exception_from_panic_info(info: &PanicHookInfo<'_>, distinct_id: &String) -> Exception {
+/// Whether a request that produced `status` is worth retrying. Transport
+/// failures are retried separately by the caller; here we only classify HTTP
+/// responses: 5xx are transient server faults and 429 is explicit throttling.
+fn is_retryable_status(status: u16) -> bool {
+    status == 429 || (500..=599).contains(&status)
+}
+
+/// Backoff before the `attempt`-th retry (0-indexed), honoring a `Retry-After`
+/// header when the server provided one.
+fn backoff_delay(options: &ClientOptions, attempt: u32, retry_after: Option<Duration>) -> Duration {
+    if let Some(delay) = retry_after {
+        return delay;
+    }
+    let multiplier = options.backoff_multiplier.powi(attempt as i32);
+    options.initial_backoff.mul_f64(multiplier)
+}
+
+/// Parses a `Retry-After` header value, which PostHog sends as an integer
+/// number of seconds on 429/503 responses.
+fn parse_retry_after(value: Option<&str>) -> Option<Duration> {
+    value
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+fn exception_from_panic_info(
+    info: &PanicHookInfo<'_>,
+    distinct_id: &String,
+    capture_stack_trace: bool,
+) -> Exception {
     let msg = message_from_panic_info(info);
     let error = SyntheticError::Panic(msg.into());
-    Exception::new(&error, distinct_id)
+    Exception::with_stack_trace(&error, distinct_id, capture_stack_trace)
 }
 
 fn message_from_panic_info<'a>(info: &'a PanicHookInfo<'_>) -> &'a str {
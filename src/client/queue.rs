@@ -0,0 +1,298 @@
+//! A batching layer over [`Client`](super::Client) that buffers events in
+//! memory and flushes them via `capture_batch` on a size threshold or a timer,
+//! whichever comes first. This keeps hot paths off the network. The panic hook
+//! installed by [`client`](crate::client) does not use this queue; it sends
+//! each panic's exception through the [`Client`](super::Client) directly.
+
+use crate::{Error, Event};
+
+/// Splits `events` into chunks of at most `max_batch_size` and sends each via
+/// `send`, collecting every error so a single failing batch doesn't hide the
+/// others.
+fn flush_batches<F>(events: Vec<Event>, max_batch_size: usize, mut send: F) -> Result<(), Vec<Error>>
+where
+    F: FnMut(Vec<Event>) -> Result<(), Error>,
+{
+    let mut errors = Vec::new();
+    let batch_size = max_batch_size.max(1);
+    let mut batch = Vec::with_capacity(batch_size);
+    for event in events {
+        batch.push(event);
+        if batch.len() >= batch_size {
+            if let Err(e) = send(std::mem::take(&mut batch)) {
+                errors.push(e);
+            }
+        }
+    }
+    if !batch.is_empty() {
+        if let Err(e) = send(batch) {
+            errors.push(e);
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(not(feature = "async-client"))]
+pub use blocking_queue::QueueingClient;
+
+#[cfg(not(feature = "async-client"))]
+mod blocking_queue {
+    use std::sync::mpsc::{self, Receiver, Sender};
+    use std::thread::{self, JoinHandle};
+    use std::time::{Duration, Instant};
+
+    use crate::{Error, Event};
+
+    use super::super::Client;
+    use super::flush_batches;
+
+    enum Message {
+        Event(Event),
+        Flush(Sender<Result<(), Vec<Error>>>),
+        Shutdown,
+    }
+
+    /// A [`Client`] wrapper that enqueues events into an in-memory buffer and
+    /// flushes them from a background thread.
+    pub struct QueueingClient {
+        sender: Sender<Message>,
+        worker: Option<JoinHandle<()>>,
+    }
+
+    impl QueueingClient {
+        pub fn new(client: Client) -> Self {
+            let (sender, receiver) = mpsc::channel();
+            let worker = thread::spawn(move || worker_loop(client, receiver));
+            Self {
+                sender,
+                worker: Some(worker),
+            }
+        }
+
+        /// Adds an event to the buffer. Never blocks on the network.
+        pub fn enqueue(&self, event: Event) -> Result<(), Error> {
+            self.sender
+                .send(Message::Event(event))
+                .map_err(|e| Error::Connection(e.to_string()))
+        }
+
+        /// Forces a flush of the buffered events and returns every error the
+        /// send produced.
+        pub fn flush(&self) -> Result<(), Vec<Error>> {
+            let (tx, rx) = mpsc::channel();
+            self.sender
+                .send(Message::Flush(tx))
+                .map_err(|e| vec![Error::Connection(e.to_string())])?;
+            rx.recv()
+                .map_err(|e| vec![Error::Connection(e.to_string())])?
+        }
+
+        /// Drains the remaining buffer and stops the background thread.
+        pub fn shutdown(&mut self) {
+            let _ = self.sender.send(Message::Shutdown);
+            if let Some(worker) = self.worker.take() {
+                let _ = worker.join();
+            }
+        }
+    }
+
+    impl Drop for QueueingClient {
+        fn drop(&mut self) {
+            self.shutdown();
+        }
+    }
+
+    fn worker_loop(client: Client, receiver: Receiver<Message>) {
+        let options = client.options();
+        let mut buffer: Vec<Event> = Vec::new();
+        let mut deadline = Instant::now() + options.flush_interval;
+
+        let flush = |buffer: &mut Vec<Event>| {
+            if buffer.is_empty() {
+                return Ok(());
+            }
+            flush_batches(std::mem::take(buffer), options.max_batch_size, |batch| {
+                client.capture_batch(batch)
+            })
+        };
+
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match receiver.recv_timeout(timeout) {
+                Ok(Message::Event(event)) => {
+                    buffer.push(event);
+                    if buffer.len() >= options.max_queue_size {
+                        let _ = flush(&mut buffer);
+                        deadline = Instant::now() + options.flush_interval;
+                    }
+                }
+                Ok(Message::Flush(reply)) => {
+                    let result = flush(&mut buffer);
+                    let _ = reply.send(result);
+                    deadline = Instant::now() + options.flush_interval;
+                }
+                Ok(Message::Shutdown) => {
+                    let _ = flush(&mut buffer);
+                    break;
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    let _ = flush(&mut buffer);
+                    deadline = Instant::now() + options.flush_interval;
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    let _ = flush(&mut buffer);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async-client")]
+pub use async_queue::QueueingClient;
+
+#[cfg(feature = "async-client")]
+mod async_queue {
+    use tokio::sync::{mpsc, oneshot};
+    use tokio::task::JoinHandle;
+    use tokio::time::{interval, MissedTickBehavior};
+
+    use crate::{Error, Event};
+
+    use super::super::Client;
+    use super::flush_batches;
+
+    enum Message {
+        Event(Event),
+        Flush(oneshot::Sender<Result<(), Vec<Error>>>),
+        Shutdown(oneshot::Sender<()>),
+    }
+
+    /// A [`Client`] wrapper that enqueues events into an in-memory buffer and
+    /// flushes them from a background task.
+    pub struct QueueingClient {
+        sender: mpsc::UnboundedSender<Message>,
+        worker: Option<JoinHandle<()>>,
+    }
+
+    impl QueueingClient {
+        pub fn new(client: Client) -> Self {
+            let (sender, receiver) = mpsc::unbounded_channel();
+            let worker = tokio::spawn(worker_loop(client, receiver));
+            Self {
+                sender,
+                worker: Some(worker),
+            }
+        }
+
+        /// Adds an event to the buffer. Never blocks on the network.
+        pub fn enqueue(&self, event: Event) -> Result<(), Error> {
+            self.sender
+                .send(Message::Event(event))
+                .map_err(|e| Error::Connection(e.to_string()))
+        }
+
+        /// Forces a flush of the buffered events and returns every error the
+        /// send produced.
+        pub async fn flush(&self) -> Result<(), Vec<Error>> {
+            let (tx, rx) = oneshot::channel();
+            self.sender
+                .send(Message::Flush(tx))
+                .map_err(|e| vec![Error::Connection(e.to_string())])?;
+            rx.await
+                .map_err(|e| vec![Error::Connection(e.to_string())])?
+        }
+
+        /// Drains the remaining buffer and stops the background task.
+        pub async fn shutdown(&mut self) {
+            let (tx, rx) = oneshot::channel();
+            if self.sender.send(Message::Shutdown(tx)).is_ok() {
+                let _ = rx.await;
+            }
+            if let Some(worker) = self.worker.take() {
+                let _ = worker.await;
+            }
+        }
+    }
+
+    impl Drop for QueueingClient {
+        fn drop(&mut self) {
+            // Best effort on an unplanned drop: signal the task to drain. A
+            // graceful exit should `await` [`shutdown`](Self::shutdown) first.
+            let (tx, _rx) = oneshot::channel();
+            let _ = self.sender.send(Message::Shutdown(tx));
+        }
+    }
+
+    async fn worker_loop(client: Client, mut receiver: mpsc::UnboundedReceiver<Message>) {
+        let mut buffer: Vec<Event> = Vec::new();
+        let mut pending: Vec<Vec<Event>> = Vec::new();
+        let mut ticker = interval(client.options().flush_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    drain(&client, &mut buffer, &mut pending).await;
+                }
+                message = receiver.recv() => {
+                    match message {
+                        Some(Message::Event(event)) => {
+                            buffer.push(event);
+                            if buffer.len() >= client.options().max_queue_size {
+                                drain(&client, &mut buffer, &mut pending).await;
+                            }
+                        }
+                        Some(Message::Flush(reply)) => {
+                            let result = drain(&client, &mut buffer, &mut pending).await;
+                            let _ = reply.send(result);
+                        }
+                        Some(Message::Shutdown(reply)) => {
+                            drain(&client, &mut buffer, &mut pending).await;
+                            let _ = reply.send(());
+                            break;
+                        }
+                        None => {
+                            drain(&client, &mut buffer, &mut pending).await;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Sends the buffered events in `max_batch_size` chunks, collecting errors.
+    async fn drain(
+        client: &Client,
+        buffer: &mut Vec<Event>,
+        pending: &mut Vec<Vec<Event>>,
+    ) -> Result<(), Vec<Error>> {
+        if buffer.is_empty() {
+            return Ok(());
+        }
+        let events = std::mem::take(buffer);
+        pending.clear();
+        let _ = flush_batches(events, client.options().max_batch_size, |batch| {
+            pending.push(batch);
+            Ok(())
+        });
+
+        let mut errors = Vec::new();
+        for batch in pending.drain(..) {
+            if let Err(e) = client.capture_batch(batch).await {
+                errors.push(e);
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
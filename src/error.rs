@@ -0,0 +1,16 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("connection error: {0}")]
+    Connection(String),
+
+    #[error("serialization error: {0}")]
+    Serialization(String),
+
+    #[error("api error: status {status}: {body}")]
+    Api { status: u16, body: String },
+
+    #[error("validation error: {0}")]
+    Validation(String),
+}
@@ -1,8 +1,11 @@
 use std::collections::HashMap;
 
+use backtrace::Backtrace;
 use chrono::NaiveDateTime;
+use rustc_demangle::demangle;
 use semver::Version;
 use serde::Serialize;
+use serde_json::{json, Value};
 
 use crate::Error;
 
@@ -14,19 +17,52 @@ pub trait EventBase {
     ) -> Result<(), Error>;
 }
 
-#[derive(Serialize, Debug, PartialEq, Eq)]
+/// Types that can be turned into a dynamic [`Event`].
+///
+/// Implemented for [`Event`] itself (an identity conversion) and derived for
+/// user structs via `#[derive(PostHogEvent)]`, so a strongly-typed event can be
+/// passed straight to [`capture`](crate::Client::capture) without assembling
+/// the properties by hand.
+pub trait PostHogEvent {
+    fn into_event(self) -> Result<Event, Error>;
+}
+
+impl PostHogEvent for Event {
+    fn into_event(self) -> Result<Event, Error> {
+        Ok(self)
+    }
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq, Clone)]
 pub struct Event {
     event: String,
     properties: Properties,
     timestamp: Option<NaiveDateTime>,
 }
 
-#[derive(Serialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Debug)]
 pub struct Exception {
     properties: Properties,
     timestamp: Option<NaiveDateTime>,
+    /// Frames captured at construction but not yet symbolized. Resolving
+    /// symbols is slow, so it is deferred until the client confirms — via
+    /// [`ClientOptions::capture_stack_trace`](crate::ClientOptions) — that a
+    /// trace is actually wanted, rather than resolving eagerly and discarding
+    /// the result.
+    #[serde(skip)]
+    backtrace: Option<Backtrace>,
+}
+
+// `Backtrace` is neither `PartialEq` nor `Eq`; it is capture state rather than
+// part of the event's identity, so equality compares the serialized fields only.
+impl PartialEq for Exception {
+    fn eq(&self, other: &Self) -> bool {
+        self.properties == other.properties && self.timestamp == other.timestamp
+    }
 }
 
+impl Eq for Exception {}
+
 #[derive(Serialize, Debug, PartialEq, Eq, Clone)]
 pub struct Properties {
     distinct_id: String,
@@ -73,6 +109,92 @@ impl Event {
     }
 }
 
+/// Property keys the crate populates itself. A user-supplied property that
+/// collides with one of these would either be overwritten or, worse, corrupt
+/// the metadata PostHog relies on, so [`Event::validate`] rejects or drops it.
+const RESERVED_PROPERTY_KEYS: &[&str] = &[
+    "$lib",
+    "$lib_name",
+    "$lib_version",
+    "$lib_version__major",
+    "$lib_version__minor",
+    "$lib_version__patch",
+    "$os",
+    "$os_version",
+    "$exception_level",
+    "$exception_list",
+];
+
+impl Event {
+    /// Checks the event before it is serialized into an [`InnerEvent`] so an
+    /// oversized or malformed payload fails locally with a precise
+    /// [`Error::Validation`] instead of being rejected by PostHog's ingestion
+    /// endpoint with an opaque 400.
+    ///
+    /// Properties colliding with the reserved `$`-prefixed keys are dropped when
+    /// `drop_invalid_properties` is set and rejected otherwise.
+    ///
+    /// The size guard measures the serialized [`InnerEvent`] — the wrapper that
+    /// is actually sent on the wire — so the enforced limit matches what
+    /// PostHog's ingestion endpoint sees, `api_key` and the injected `$lib_*`
+    /// properties included.
+    pub(crate) fn validate(
+        &mut self,
+        api_key: &str,
+        max_payload_size: usize,
+        max_properties: usize,
+        drop_invalid_properties: bool,
+    ) -> Result<(), Error> {
+        if self.event.trim().is_empty() {
+            return Err(Error::Validation("event name must not be empty".to_string()));
+        }
+
+        let mut reserved: Vec<String> = self
+            .properties
+            .props
+            .keys()
+            .filter(|key| RESERVED_PROPERTY_KEYS.contains(&key.as_str()))
+            .cloned()
+            .collect();
+        if !reserved.is_empty() {
+            if drop_invalid_properties {
+                for key in &reserved {
+                    self.properties.props.remove(key);
+                }
+            } else {
+                reserved.sort();
+                return Err(Error::Validation(format!(
+                    "event \"{}\" sets reserved property keys: {}",
+                    self.event,
+                    reserved.join(", ")
+                )));
+            }
+        }
+
+        if self.properties.props.len() > max_properties {
+            return Err(Error::Validation(format!(
+                "event \"{}\" has {} properties, exceeding the limit of {}",
+                self.event,
+                self.properties.props.len(),
+                max_properties
+            )));
+        }
+
+        let inner_event = InnerEvent::new(self.clone(), api_key.to_string());
+        let size = serde_json::to_vec(&inner_event)
+            .map_err(|e| Error::Serialization(e.to_string()))?
+            .len();
+        if size > max_payload_size {
+            return Err(Error::Validation(format!(
+                "event \"{}\" serializes to {} bytes, exceeding the limit of {}",
+                self.event, size, max_payload_size
+            )));
+        }
+
+        Ok(())
+    }
+}
+
 impl EventBase for Event {
     /// Errors if `prop` fails to serialize
     fn insert_prop<K: Into<String>, P: Serialize>(
@@ -89,12 +211,42 @@ impl EventBase for Event {
 
 impl Exception {
     pub fn new<S: Into<String>>(exception: &dyn std::error::Error, distinct_id: S) -> Self {
-        Self {
+        Self::with_stack_trace(exception, distinct_id, true)
+    }
+
+    /// Builds an exception without capturing a stack trace. Callers on hot paths
+    /// that do not want frames can skip the capture entirely instead of paying
+    /// for symbol resolution and discarding the result.
+    pub fn new_without_stack_trace<S: Into<String>>(
+        exception: &dyn std::error::Error,
+        distinct_id: S,
+    ) -> Self {
+        Self::with_stack_trace(exception, distinct_id, false)
+    }
+
+    /// Builds an exception, capturing the call stack only when
+    /// `capture_stack_trace` is set. The frames are recorded unsymbolized here;
+    /// the slow symbol resolution is deferred to [`resolve_stack_trace`] so that
+    /// a disabled [`ClientOptions::capture_stack_trace`] skips it entirely.
+    ///
+    /// [`resolve_stack_trace`]: Exception::resolve_stack_trace
+    /// [`ClientOptions::capture_stack_trace`]: crate::ClientOptions
+    pub(crate) fn with_stack_trace<S: Into<String>>(
+        exception: &dyn std::error::Error,
+        distinct_id: S,
+        capture_stack_trace: bool,
+    ) -> Self {
+        let mut this = Self {
             properties: Properties::new(distinct_id),
             timestamp: None,
+            backtrace: None,
         }
             .with_exception_level(Some("error".to_string()))
-            .set_exception_list(exception)
+            .set_exception_list(exception);
+        if capture_stack_trace {
+            this.backtrace = Some(Backtrace::new_unresolved());
+        }
+        this
     }
 
     pub fn with_exception_level(mut self, exception_level: Option<String>) -> Self {
@@ -111,8 +263,6 @@ impl Exception {
         mechanism.insert("synthetic".into(), serde_json::Value::Bool(false));
         exception_info.insert("mechanism".into(), serde_json::Value::Object(mechanism));
 
-        //TODO: Parse and add stacktrace
-
         self.properties.exception_list = Some(serde_json::Value::Array(vec![serde_json::Value::Object(exception_info)]));
         self
     }
@@ -139,6 +289,31 @@ impl Exception {
         exception_type
     }
 
+    /// Symbolizes the captured backtrace (if any) and attaches it to the
+    /// `$exception_list` entry in the `stacktrace` shape PostHog expects.
+    ///
+    /// Symbol resolution is the slow part of stack-trace capture, so it is done
+    /// here — invoked by the client only when
+    /// [`ClientOptions::capture_stack_trace`](crate::ClientOptions) is enabled —
+    /// rather than eagerly at construction. Does nothing when the exception was
+    /// built without a captured trace.
+    pub(crate) fn resolve_stack_trace(&mut self) {
+        let mut backtrace = match self.backtrace.take() {
+            Some(backtrace) => backtrace,
+            None => return,
+        };
+        backtrace.resolve();
+        let stacktrace = match render_stacktrace(&backtrace) {
+            Some(stacktrace) => stacktrace,
+            None => return,
+        };
+        if let Some(Value::Array(list)) = self.properties.exception_list.as_mut() {
+            if let Some(Value::Object(entry)) = list.first_mut() {
+                entry.insert("stacktrace".into(), stacktrace);
+            }
+        }
+    }
+
     pub fn to_event(&self) -> Event {
         let mut event = Event::new("$exception", &self.properties.distinct_id);
         event.timestamp = self.timestamp;
@@ -147,6 +322,85 @@ impl Exception {
     }
 }
 
+/// Renders a resolved backtrace into the `stacktrace` object PostHog's
+/// error-tracking UI expects: `{ "type": "raw", "frames": [ .. ] }`.
+///
+/// Frames are ordered oldest-caller-first and the innermost frames belonging to
+/// posthog-rs's own capture machinery (the panic hook and [`Exception::new`])
+/// or the `backtrace` crate are dropped. Only those frames are dropped: for a
+/// panic, the standard library's panic-handling frames between the hook and
+/// the panicking code are kept.
+fn render_stacktrace(backtrace: &Backtrace) -> Option<Value> {
+    let mut frames = Vec::new();
+    let mut reached_user_code = false;
+    for frame in backtrace.frames() {
+        for symbol in frame.symbols() {
+            // `SymbolName::as_bytes()` is the raw, still-mangled symbol;
+            // `rustc_demangle` turns it into a readable name (and passes any
+            // already-unmangled input through unchanged).
+            let function = symbol.name().map(|name| {
+                demangle(&String::from_utf8_lossy(name.as_bytes())).to_string()
+            });
+
+            // Skip the innermost frames that live inside this crate (and the
+            // `backtrace` capture itself) until the first frame outside them.
+            if !reached_user_code {
+                if function.as_deref().is_some_and(is_capture_frame) {
+                    continue;
+                }
+                reached_user_code = true;
+            }
+
+            let filename = symbol.filename().map(|path| path.display().to_string());
+            let in_app = filename.as_deref().is_some_and(is_in_app);
+            let raw_id = symbol
+                .addr()
+                .map(|addr| format!("{addr:p}"))
+                .unwrap_or_default();
+
+            frames.push(json!({
+                "raw_id": raw_id,
+                "function": function,
+                "filename": filename,
+                "lineno": symbol.lineno(),
+                "colno": symbol.colno(),
+                "in_app": in_app,
+                "platform": "rust",
+                "lang": "rust",
+            }));
+        }
+    }
+
+    if frames.is_empty() {
+        return None;
+    }
+
+    // PostHog renders frames oldest-caller-first; `backtrace` yields them
+    // innermost-first, so reverse before emitting.
+    frames.reverse();
+    Some(json!({ "type": "raw", "frames": frames }))
+}
+
+/// Frames inside posthog-rs's own capture path or the `backtrace` crate, which
+/// are noise relative to the real crash site.
+fn is_capture_frame(function: &str) -> bool {
+    function.starts_with("posthog")
+        || function.starts_with("backtrace::")
+        || function.starts_with("<posthog")
+}
+
+/// Heuristic for whether a frame belongs to the user's own crate rather than a
+/// dependency, the standard library, or the toolchain.
+fn is_in_app(filename: &str) -> bool {
+    !(filename.contains("/backtrace")
+        || filename.contains("posthog")
+        || filename.contains("/rustc/")
+        || filename.contains("/.cargo/registry")
+        || filename.contains("library/std")
+        || filename.contains("library/core")
+        || filename.contains("library/alloc"))
+}
+
 impl EventBase for Exception {
     fn insert_prop<K: Into<String>, P: Serialize>(
         &mut self,
@@ -211,7 +465,7 @@ impl InnerEvent {
 
 #[cfg(test)]
 pub mod tests {
-    use crate::{event::InnerEvent, Event, EventBase};
+    use crate::{event::InnerEvent, Error, Event, EventBase};
 
     #[test]
     fn inner_event_adds_lib_properties_correctly() {
@@ -230,4 +484,20 @@ pub mod tests {
             Some(&serde_json::Value::String("posthog-rs".to_string()))
         );
     }
+
+    #[test]
+    fn validate_rejects_reserved_property_keys() {
+        // Arrange
+        let mut event = Event::new("unit test event", "1234");
+        event.insert_prop("$lib_version", "9.9.9").unwrap();
+
+        // Act
+        let rejected = event.validate("test_api_key", 1_000_000, 1024, false);
+        let dropped = event.validate("test_api_key", 1_000_000, 1024, true);
+
+        // Assert
+        assert!(matches!(rejected, Err(Error::Validation(_))));
+        assert!(dropped.is_ok());
+        assert!(!event.properties.props.contains_key("$lib_version"));
+    }
 }
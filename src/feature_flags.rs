@@ -0,0 +1,513 @@
+//! Feature-flag evaluation.
+//!
+//! Two modes are supported. In *remote* mode the client POSTs to PostHog's
+//! `/flags` (decide) endpoint and reads back the resolved `featureFlags` /
+//! `featureFlagPayloads` maps. In *local* mode flag definitions are pulled
+//! periodically and evaluated offline using the same deterministic rollout
+//! math PostHog applies server-side, falling back to the remote endpoint for
+//! flags whose condition groups carry property filters we cannot resolve.
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+
+/// The denominator PostHog divides the truncated hash by to produce a float in
+/// `[0, 1)`. This exact constant keeps local decisions identical to the
+/// server's.
+const LONG_SCALE: u64 = 0xfffffffffffffff;
+
+/// Deterministic hash PostHog uses to place a `distinct_id` on the `[0, 1)`
+/// rollout line for a given flag `key`.
+///
+/// `salt` separates the two uses of the hash: it is empty for the rollout
+/// check of every condition group, so overlapping rollouts stay stable across
+/// groups, and `"variant"` when picking a multivariate value.
+pub(crate) fn flag_hash(key: &str, distinct_id: &str, salt: &str) -> f64 {
+    let mut hasher = Sha1::new();
+    hasher.update(format!("{key}.{distinct_id}{salt}").as_bytes());
+    let digest = hasher.finalize();
+
+    let hex = digest
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+
+    // First 15 hex characters parsed as an integer, divided by `0xfffffffffffffff`.
+    let value = u64::from_str_radix(&hex[..15], 16).expect("15 hex chars always parse");
+    value as f64 / LONG_SCALE as f64
+}
+
+/// A resolved flag value: either a boolean flag or the selected variant of a
+/// multivariate flag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FlagValue {
+    Bool(bool),
+    Variant(String),
+}
+
+impl FlagValue {
+    /// Whether the flag is enabled. A variant is always considered enabled.
+    pub fn is_enabled(&self) -> bool {
+        match self {
+            FlagValue::Bool(value) => *value,
+            FlagValue::Variant(_) => true,
+        }
+    }
+
+    pub fn variant(&self) -> Option<&str> {
+        match self {
+            FlagValue::Variant(name) => Some(name),
+            FlagValue::Bool(_) => None,
+        }
+    }
+}
+
+/// A flag carries a condition group with property filters that cannot be
+/// evaluated without person data the client doesn't hold, so the caller must
+/// fall back to the remote endpoint.
+pub(crate) struct RequiresRemoteEvaluation;
+
+/// A flag definition as returned by the local-evaluation endpoint.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct FlagDefinition {
+    pub key: String,
+    #[serde(default)]
+    pub active: bool,
+    #[serde(default)]
+    pub filters: FlagFilters,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub(crate) struct FlagFilters {
+    #[serde(default)]
+    pub groups: Vec<ConditionGroup>,
+    #[serde(default)]
+    pub multivariate: Option<Multivariate>,
+    #[serde(default)]
+    pub payloads: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ConditionGroup {
+    #[serde(default)]
+    pub properties: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub rollout_percentage: Option<f64>,
+    #[serde(default)]
+    pub variant: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct Multivariate {
+    #[serde(default)]
+    pub variants: Vec<Variant>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct Variant {
+    pub key: String,
+    pub rollout_percentage: f64,
+}
+
+impl FlagDefinition {
+    /// Evaluates the flag offline for `distinct_id`.
+    ///
+    /// Returns `Err(RequiresRemoteEvaluation)` as soon as a condition group
+    /// carries property filters, since resolving those needs person data only
+    /// the `/flags` endpoint has.
+    pub fn evaluate(&self, distinct_id: &str) -> Result<FlagValue, RequiresRemoteEvaluation> {
+        if !self.active {
+            return Ok(FlagValue::Bool(false));
+        }
+
+        for group in &self.filters.groups {
+            if !group.properties.is_empty() {
+                return Err(RequiresRemoteEvaluation);
+            }
+
+            let rollout = group.rollout_percentage.unwrap_or(100.0) / 100.0;
+            if flag_hash(&self.key, distinct_id, "") <= rollout {
+                if let Some(variant) = &group.variant {
+                    return Ok(FlagValue::Variant(variant.clone()));
+                }
+                if let Some(variant) = self.pick_variant(distinct_id) {
+                    return Ok(FlagValue::Variant(variant));
+                }
+                return Ok(FlagValue::Bool(true));
+            }
+        }
+
+        Ok(FlagValue::Bool(false))
+    }
+
+    /// Selects a multivariate value by walking the variants in order and
+    /// assigning the `distinct_id` to the first whose cumulative rollout window
+    /// contains its (variant-salted) hash.
+    fn pick_variant(&self, distinct_id: &str) -> Option<String> {
+        let multivariate = self.filters.multivariate.as_ref()?;
+        let hash = flag_hash(&self.key, distinct_id, "variant");
+        let mut cumulative = 0.0;
+        for variant in &multivariate.variants {
+            cumulative += variant.rollout_percentage / 100.0;
+            if hash < cumulative {
+                return Some(variant.key.clone());
+            }
+        }
+        None
+    }
+
+    /// Returns the payload configured for `value`, if any.
+    pub fn payload(&self, value: &FlagValue) -> Option<serde_json::Value> {
+        let lookup = match value {
+            FlagValue::Variant(name) => name.as_str(),
+            FlagValue::Bool(true) => "true",
+            FlagValue::Bool(false) => return None,
+        };
+        self.filters.payloads.get(lookup).cloned()
+    }
+}
+
+/// Response body of a `/flags` (decide) request.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub(crate) struct DecideResponse {
+    #[serde(rename = "featureFlags", default)]
+    pub feature_flags: HashMap<String, serde_json::Value>,
+    #[serde(rename = "featureFlagPayloads", default)]
+    pub feature_flag_payloads: HashMap<String, serde_json::Value>,
+}
+
+impl DecideResponse {
+    /// Interprets a single `featureFlags` entry, which PostHog encodes as
+    /// either a boolean or a variant string.
+    pub fn flag(&self, key: &str) -> Option<FlagValue> {
+        self.feature_flags.get(key).map(|value| match value {
+            serde_json::Value::Bool(enabled) => FlagValue::Bool(*enabled),
+            serde_json::Value::String(variant) => FlagValue::Variant(variant.clone()),
+            other => FlagValue::Bool(!other.is_null()),
+        })
+    }
+}
+
+#[cfg(not(feature = "async-client"))]
+pub use blocking_poller::FeatureFlagPoller;
+
+#[cfg(not(feature = "async-client"))]
+mod blocking_poller {
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, RwLock};
+    use std::thread::{self, JoinHandle};
+
+    use crate::{Client, Error};
+
+    use super::{FlagDefinition, FlagValue};
+
+    type Cache = Arc<RwLock<HashMap<String, FlagDefinition>>>;
+
+    /// Periodically refreshes flag definitions in the background and evaluates
+    /// them offline, falling back to the remote `/flags` endpoint for flags it
+    /// cannot resolve locally.
+    pub struct FeatureFlagPoller {
+        client: Client,
+        cache: Cache,
+        running: Arc<AtomicBool>,
+        worker: Option<JoinHandle<()>>,
+    }
+
+    impl FeatureFlagPoller {
+        /// Starts the poller, performing an initial blocking refresh so the
+        /// first lookup has definitions to work with.
+        pub fn start(client: Client) -> Result<Self, Error> {
+            let cache: Cache = Arc::new(RwLock::new(HashMap::new()));
+            refresh(&client, &cache)?;
+
+            let running = Arc::new(AtomicBool::new(true));
+            let worker = {
+                let client = client.clone();
+                let cache = Arc::clone(&cache);
+                let running = Arc::clone(&running);
+                let interval = client.poll_interval();
+                thread::spawn(move || {
+                    while running.load(Ordering::Relaxed) {
+                        thread::sleep(interval);
+                        if !running.load(Ordering::Relaxed) {
+                            break;
+                        }
+                        let _ = refresh(&client, &cache);
+                    }
+                })
+            };
+
+            Ok(Self {
+                client,
+                cache,
+                running,
+                worker: Some(worker),
+            })
+        }
+
+        pub fn is_feature_enabled(&self, key: &str, distinct_id: &str) -> Result<bool, Error> {
+            Ok(self
+                .get_feature_flag(key, distinct_id)?
+                .map(|value| value.is_enabled())
+                .unwrap_or(false))
+        }
+
+        pub fn get_feature_flag(
+            &self,
+            key: &str,
+            distinct_id: &str,
+        ) -> Result<Option<FlagValue>, Error> {
+            let local = {
+                let cache = self.cache.read().expect("cache lock poisoned");
+                cache.get(key).map(|def| def.evaluate(distinct_id))
+            };
+            match local {
+                Some(Ok(value)) => Ok(Some(value)),
+                // Unknown flag, or a property filter we can't resolve locally:
+                // defer to the remote endpoint.
+                _ => self.client.get_feature_flag(key, distinct_id),
+            }
+        }
+
+        pub fn get_feature_flag_payload(
+            &self,
+            key: &str,
+            distinct_id: &str,
+        ) -> Result<Option<serde_json::Value>, Error> {
+            let local = {
+                let cache = self.cache.read().expect("cache lock poisoned");
+                cache
+                    .get(key)
+                    .map(|def| (def.evaluate(distinct_id), def.clone()))
+            };
+            if let Some((Ok(value), def)) = local {
+                return Ok(def.payload(&value));
+            }
+            self.client.get_feature_flag_payload(key, distinct_id)
+        }
+    }
+
+    impl Drop for FeatureFlagPoller {
+        fn drop(&mut self) {
+            self.running.store(false, Ordering::Relaxed);
+            if let Some(worker) = self.worker.take() {
+                let _ = worker.join();
+            }
+        }
+    }
+
+    fn refresh(client: &Client, cache: &Cache) -> Result<(), Error> {
+        let definitions = client.fetch_flag_definitions()?;
+        let mut cache = cache.write().expect("cache lock poisoned");
+        cache.clear();
+        for definition in definitions {
+            cache.insert(definition.key.clone(), definition);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async-client")]
+pub use async_poller::FeatureFlagPoller;
+
+#[cfg(feature = "async-client")]
+mod async_poller {
+    use std::collections::HashMap;
+    use std::sync::{Arc, RwLock};
+
+    use tokio::task::JoinHandle;
+    use tokio::time::{interval, MissedTickBehavior};
+
+    use crate::{Client, Error};
+
+    use super::{FlagDefinition, FlagValue};
+
+    type Cache = Arc<RwLock<HashMap<String, FlagDefinition>>>;
+
+    /// Periodically refreshes flag definitions in a background task and
+    /// evaluates them offline, falling back to the remote `/flags` endpoint for
+    /// flags it cannot resolve locally.
+    pub struct FeatureFlagPoller {
+        client: Client,
+        cache: Cache,
+        worker: JoinHandle<()>,
+    }
+
+    impl FeatureFlagPoller {
+        /// Starts the poller, performing an initial refresh so the first lookup
+        /// has definitions to work with.
+        pub async fn start(client: Client) -> Result<Self, Error> {
+            let cache: Cache = Arc::new(RwLock::new(HashMap::new()));
+            refresh(&client, &cache).await?;
+
+            let worker = {
+                let client = client.clone();
+                let cache = Arc::clone(&cache);
+                let poll_interval = client.poll_interval();
+                tokio::spawn(async move {
+                    let mut ticker = interval(poll_interval);
+                    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                    // Skip the immediate first tick; the initial refresh already ran.
+                    ticker.tick().await;
+                    loop {
+                        ticker.tick().await;
+                        let _ = refresh(&client, &cache).await;
+                    }
+                })
+            };
+
+            Ok(Self {
+                client,
+                cache,
+                worker,
+            })
+        }
+
+        pub async fn is_feature_enabled(&self, key: &str, distinct_id: &str) -> Result<bool, Error> {
+            Ok(self
+                .get_feature_flag(key, distinct_id)
+                .await?
+                .map(|value| value.is_enabled())
+                .unwrap_or(false))
+        }
+
+        pub async fn get_feature_flag(
+            &self,
+            key: &str,
+            distinct_id: &str,
+        ) -> Result<Option<FlagValue>, Error> {
+            let local = {
+                let cache = self.cache.read().expect("cache lock poisoned");
+                cache.get(key).map(|def| def.evaluate(distinct_id))
+            };
+            match local {
+                Some(Ok(value)) => Ok(Some(value)),
+                _ => self.client.get_feature_flag(key, distinct_id).await,
+            }
+        }
+
+        pub async fn get_feature_flag_payload(
+            &self,
+            key: &str,
+            distinct_id: &str,
+        ) -> Result<Option<serde_json::Value>, Error> {
+            let local = {
+                let cache = self.cache.read().expect("cache lock poisoned");
+                cache
+                    .get(key)
+                    .map(|def| (def.evaluate(distinct_id), def.clone()))
+            };
+            if let Some((Ok(value), def)) = local {
+                return Ok(def.payload(&value));
+            }
+            self.client.get_feature_flag_payload(key, distinct_id).await
+        }
+    }
+
+    impl Drop for FeatureFlagPoller {
+        fn drop(&mut self) {
+            self.worker.abort();
+        }
+    }
+
+    async fn refresh(client: &Client, cache: &Cache) -> Result<(), Error> {
+        let definitions = client.fetch_flag_definitions().await?;
+        let mut cache = cache.write().expect("cache lock poisoned");
+        cache.clear();
+        for definition in definitions {
+            cache.insert(definition.key.clone(), definition);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn group(rollout: f64) -> ConditionGroup {
+        ConditionGroup {
+            properties: Vec::new(),
+            rollout_percentage: Some(rollout),
+            variant: None,
+        }
+    }
+
+    fn boolean_flag(key: &str, groups: Vec<ConditionGroup>) -> FlagDefinition {
+        FlagDefinition {
+            key: key.to_string(),
+            active: true,
+            filters: FlagFilters {
+                groups,
+                multivariate: None,
+                payloads: HashMap::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn flag_hash_matches_posthog_reference() {
+        // Pinned against an independent reference computation of
+        // `int(sha1("{key}.{distinct_id}{salt}")[..15], 16) / 0xfffffffffffffff`;
+        // a drift here means local decisions diverge from the server's.
+        assert!((flag_hash("beta-feature", "test_id", "") - 0.9417728557208149).abs() < 1e-12);
+        assert!((flag_hash("beta-feature", "test_id", "variant") - 0.7786746491848937).abs() < 1e-12);
+    }
+
+    #[test]
+    fn evaluate_brackets_the_rollout_boundary() {
+        // `flag_hash("my-flag", "user-1", "")` is ~0.1634, so a 17% rollout
+        // includes the user while a 16% rollout excludes them.
+        let included = boolean_flag("my-flag", vec![group(17.0)]);
+        let excluded = boolean_flag("my-flag", vec![group(16.0)]);
+
+        assert_eq!(included.evaluate("user-1").ok(), Some(FlagValue::Bool(true)));
+        assert_eq!(excluded.evaluate("user-1").ok(), Some(FlagValue::Bool(false)));
+    }
+
+    #[test]
+    fn evaluate_is_inclusive_at_the_rollout_edge() {
+        // The rollout test is `hash <= rollout`, so a rollout set exactly to the
+        // user's hash must still enable the flag.
+        let hash = flag_hash("my-flag", "user-1", "");
+        let flag = boolean_flag("my-flag", vec![group(hash * 100.0)]);
+
+        assert_eq!(flag.evaluate("user-1").ok(), Some(FlagValue::Bool(true)));
+    }
+
+    #[test]
+    fn evaluate_splits_multivariate_deterministically() {
+        // `flag_hash("beta-feature", "test_id", "variant")` is ~0.7787; with two
+        // 50% variants the cumulative windows are [0, 0.5) and [0.5, 1.0), so the
+        // user lands in the second, "test".
+        let flag = FlagDefinition {
+            key: "beta-feature".to_string(),
+            active: true,
+            filters: FlagFilters {
+                groups: vec![group(100.0)],
+                multivariate: Some(Multivariate {
+                    variants: vec![
+                        Variant {
+                            key: "control".to_string(),
+                            rollout_percentage: 50.0,
+                        },
+                        Variant {
+                            key: "test".to_string(),
+                            rollout_percentage: 50.0,
+                        },
+                    ],
+                }),
+                payloads: HashMap::new(),
+            },
+        };
+
+        assert_eq!(
+            flag.evaluate("test_id").ok(),
+            Some(FlagValue::Variant("test".to_string()))
+        );
+    }
+}
@@ -1,6 +1,7 @@
 mod client;
 mod error;
 mod event;
+mod feature_flags;
 
 const API_ENDPOINT: &str = "https://us.i.posthog.com/capture/";
 
@@ -10,6 +11,7 @@ pub use client::client;
 pub use client::Client;
 pub use client::ClientOptions;
 pub use client::ClientOptionsBuilder;
+pub use client::QueueingClient;
 
 // Error
 pub use error::Error;
@@ -17,8 +19,16 @@ pub use error::Error;
 // EventBase
 pub use event::EventBase;
 
+// PostHogEvent: the conversion trait and its companion derive macro
+pub use event::PostHogEvent;
+pub use posthog_rs_macros::PostHogEvent;
+
 // Event
 pub use event::Event;
 
 // Exception
-pub use event::Exception;
\ No newline at end of file
+pub use event::Exception;
+
+// Feature flags
+pub use feature_flags::FlagValue;
+pub use feature_flags::FeatureFlagPoller;
\ No newline at end of file